@@ -13,18 +13,18 @@ extern crate cursive;
 // STD Dependencies -----------------------------------------------------------
 use std::cmp;
 use std::rc::Rc;
-use std::cell::RefCell;
 use std::fmt::Display;
 
 
 // External Dependencies ------------------------------------------------------
 use cursive::With;
 use cursive::vec::Vec2;
-use cursive::view::{ScrollBase, View};
+use cursive::view::{Identifiable, IdView, ScrollBase, View};
 use cursive::theme::ColorStyle;
+use cursive::utils::markup::StyledString;
 use cursive::{Cursive, Printer};
 use cursive::direction::Direction;
-use cursive::event::{Callback, Event, EventResult, Key};
+use cursive::event::{Callback, Event, EventResult, Key, MouseButton, MouseEvent};
 
 
 // Internal Dependencies ------------------------------------------------------
@@ -33,6 +33,19 @@ use tree_list::TreeList;
 pub use tree_list::Placement;
 
 
+/// Contextual information about a row, passed to a custom renderer set via
+/// [`TreeView::set_renderer`](struct.TreeView.html#method.set_renderer).
+pub struct RowContext {
+    /// The nesting depth of the row, `0` for top level items.
+    pub level: usize,
+    /// Whether the row's children are currently hidden.
+    pub collapsed: bool,
+    /// Whether the row has any children, loaded or not.
+    pub has_children: bool,
+    /// Whether the row is the currently focused row.
+    pub focused: bool
+}
+
 /// View to select an item among a tree.
 ///
 /// # Examples
@@ -56,10 +69,16 @@ pub struct TreeView<T: Display> {
     on_submit: Option<Rc<Fn(&mut Cursive, usize)>>,
     on_select: Option<Rc<Fn(&mut Cursive, usize)>>,
     on_collapse: Option<Rc<Fn(&mut Cursive, usize, bool)>>,
+    on_expand: Option<Rc<Fn(&mut Cursive, usize) -> Vec<(T, Placement)>>>,
+    sorter: Option<Rc<Fn(&T, &T) -> cmp::Ordering>>,
+    renderer: Option<Rc<Fn(&T, RowContext) -> StyledString>>,
 
+    id: Rc<String>,
     scrollbase: ScrollBase,
     last_size: Vec2,
     focus: usize,
+    searching: bool,
+    search_query: String,
     list: TreeList<T>
 }
 
@@ -72,10 +91,16 @@ impl<T: Display> TreeView<T> {
             on_submit: None,
             on_select: None,
             on_collapse: None,
+            on_expand: None,
+            sorter: None,
+            renderer: None,
 
+            id: Rc::new(String::new()),
             scrollbase: ScrollBase::new(),
             last_size: (0, 0).into(),
             focus: 0,
+            searching: false,
+            search_query: String::new(),
             list: TreeList::new()
         }
     }
@@ -200,6 +225,127 @@ impl<T: Display> TreeView<T> {
         self.with(|t| t.set_on_collapse(cb))
     }
 
+    /// Sets a callback to be used for loading the children of a lazy,
+    /// not-yet-loaded item.
+    ///
+    /// The callback is invoked the first time such an item is expanded and
+    /// must return the items to insert as its children, along with the
+    /// `Placement` of each relative to the previous one. See
+    /// `insert_lazy_item`.
+    ///
+    /// Loading the returned children requires `&mut Cursive`, so the view
+    /// looks itself back up via `Cursive::call_on_id` once the callback
+    /// returns. This only works if the view was wrapped with
+    /// [`TreeView::with_id`](struct.TreeView.html#method.with_id) (rather
+    /// than the generic `Identifiable::with_id`/`Nameable::with_name`);
+    /// without it, expanding a lazy item is silently ignored instead of
+    /// loading its children.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate cursive;
+    /// # extern crate cursive_tree_view;
+    /// # use cursive::Cursive;
+    /// # use cursive_tree_view::{TreeView, Placement};
+    /// # fn main() {
+    /// # let mut tree: TreeView<String> = TreeView::new();
+    /// tree.set_on_expand(|_: &mut Cursive, _row: usize| {
+    ///     vec![("a".to_string(), Placement::Child)]
+    /// });
+    /// # }
+    /// ```
+    pub fn set_on_expand<F>(&mut self, cb: F)
+        where F: Fn(&mut Cursive, usize) -> Vec<(T, Placement)> + 'static
+    {
+        self.on_expand = Some(Rc::new(move |s, row| cb(s, row)));
+    }
+
+    /// Sets a callback to be used for loading the children of a lazy,
+    /// not-yet-loaded item.
+    ///
+    /// Chainable variant.
+    pub fn on_expand<F>(self, cb: F) -> Self
+        where F: Fn(&mut Cursive, usize) -> Vec<(T, Placement)> + 'static
+    {
+        self.with(|t| t.set_on_expand(cb))
+    }
+
+    /// Sets the comparator used to order the children of a node.
+    ///
+    /// Once set, items inserted as [`Placement::Child`](enum.Placement.html)
+    /// via `insert_item` are placed at their sorted position rather than
+    /// strictly at the requested row. Such an insertion re-sorts all direct
+    /// children of the new item's parent; other existing children are left
+    /// untouched until `sort_children` or `sort_children_recursive` is
+    /// called.
+    pub fn set_sorter<F>(&mut self, cmp: F)
+        where F: Fn(&T, &T) -> cmp::Ordering + 'static
+    {
+        self.sorter = Some(Rc::new(cmp));
+    }
+
+    /// Sets the comparator used to order the children of a node.
+    ///
+    /// Chainable variant.
+    pub fn sorter<F>(self, cmp: F) -> Self
+        where F: Fn(&T, &T) -> cmp::Ordering + 'static
+    {
+        self.with(|t| t.set_sorter(cmp))
+    }
+
+    /// Sorts the immediate children of the given `row` using the
+    /// comparator set via `set_sorter`. Does nothing if no sorter is set.
+    pub fn sort_children(&mut self, row: usize) {
+        if let Some(sorter) = self.sorter.clone() {
+            let index = self.list.visual_index(row);
+            self.list.sort_children(index, false, &*sorter);
+        }
+    }
+
+    /// Sorts the children of the given `row`, and recursively the children
+    /// of every descendant, using the comparator set via `set_sorter`.
+    /// Does nothing if no sorter is set.
+    pub fn sort_children_recursive(&mut self, row: usize) {
+        if let Some(sorter) = self.sorter.clone() {
+            let index = self.list.visual_index(row);
+            self.list.sort_children(index, true, &*sorter);
+        }
+    }
+
+    /// Sets the renderer used to draw each row.
+    ///
+    /// When set, the renderer is responsible for the entire contents of a
+    /// row, including its fold glyph and any icon; the built-in `▸`/`▾`/`◦`
+    /// glyphs and single-color `Display` text are no longer drawn. See
+    /// [`RowContext`](struct.RowContext.html) for the information made
+    /// available about the row being rendered.
+    pub fn set_renderer<F>(&mut self, renderer: F)
+        where F: Fn(&T, RowContext) -> StyledString + 'static
+    {
+        self.renderer = Some(Rc::new(renderer));
+    }
+
+    /// Sets the renderer used to draw each row.
+    ///
+    /// Chainable variant.
+    pub fn renderer<F>(self, renderer: F) -> Self
+        where F: Fn(&T, RowContext) -> StyledString + 'static
+    {
+        self.with(|t| t.set_renderer(renderer))
+    }
+
+    /// Wraps this view so it can be looked up by `id`.
+    ///
+    /// This also stores the `id` internally so the view can refer back to
+    /// itself via `Cursive::call_on_id` from within callbacks that need
+    /// `&mut Cursive`, such as the lazy-loading expansion callback set via
+    /// `set_on_expand`.
+    pub fn with_id(mut self, id: &str) -> IdView<Self> {
+        self.id = Rc::new(id.to_string());
+        Identifiable::with_id(self, id)
+    }
+
     /// Removes all items from this view.
     pub fn clear(&mut self) {
         self.list.clear();
@@ -225,7 +371,7 @@ impl<T: Display> TreeView<T> {
 
     /// Returns the index of the currently selected table row.
     pub fn row(&self) -> Option<usize> {
-        if self.is_empty() {
+        if self.no_visible_rows() {
             None
 
         } else {
@@ -258,12 +404,54 @@ impl<T: Display> TreeView<T> {
         self.list.get_mut(index)
     }
 
+    /// Returns a immutable reference to the item at the given row, or
+    /// `None` if `row_index` is out of bounds.
+    ///
+    /// The fallible counterpart to `borrow_item`, for callers that do not
+    /// already know `row_index` is in range.
+    pub fn try_borrow_item(&mut self, row_index: usize) -> Option<&T> {
+        if row_index < self.list.height() {
+            self.borrow_item(row_index)
+
+        } else {
+            None
+        }
+    }
+
     /// Inserts a new `item` at the given `row` with the specified
-    /// [`Placement`](enum.Placement.html), returning the row index of the item
-    /// occupies after its insertion.
+    /// [`Placement`](enum.Placement.html), returning the index the item
+    /// occupies in the flattened tree after its insertion. This equals its
+    /// row index while none of its ancestors is collapsed and no filter is
+    /// active.
+    ///
+    /// If a sorter is set via `set_sorter` and `placement` is
+    /// [`Placement::Child`](enum.Placement.html), the item's parent is
+    /// re-sorted afterwards and the returned index reflects the item's
+    /// final, sorted position rather than where it was first inserted.
     pub fn insert_item(&mut self, item: T, placement: Placement, row: usize) -> usize {
         let index = self.list.visual_index(row);
-        self.list.insert(placement, index, item)
+        let new_index = self.list.insert(placement, index, item);
+
+        if placement == Placement::Child {
+            if let Some(sorter) = self.sorter.clone() {
+                if let Some(sorted_index) = self.list.sort_children_tracking(index, false, &*sorter, Some(new_index)) {
+                    return sorted_index;
+                }
+            }
+        }
+
+        new_index
+    }
+
+    /// Inserts a lazily loaded placeholder `item` at the given `row` with
+    /// the specified [`Placement`](enum.Placement.html).
+    ///
+    /// The item is shown collapsed with a fold glyph but no children; its
+    /// actual children are fetched on first expansion via the callback set
+    /// with `set_on_expand`.
+    pub fn insert_lazy_item(&mut self, item: T, placement: Placement, row: usize) -> usize {
+        let index = self.list.visual_index(row);
+        self.list.insert_lazy(placement, index, item)
     }
 
     /// Removes the item at the given `row` along with all of its children.
@@ -272,17 +460,31 @@ impl<T: Display> TreeView<T> {
     pub fn remove_item(&mut self, row: usize) -> Option<Vec<T>> {
         let index = self.list.visual_index(row);
         let removed = self.list.remove_with_children(index);
-        self.focus = cmp::min(self.focus, self.list.height() - 1);
+        self.focus = cmp::min(self.focus, self.list.height().saturating_sub(1));
         removed
     }
 
+    /// Removes the item at the given `row` along with all of its children,
+    /// returning `None` without touching the tree if `row` is out of
+    /// bounds.
+    ///
+    /// The fallible counterpart to `remove_item`.
+    pub fn try_remove_item(&mut self, row: usize) -> Option<Vec<T>> {
+        if row < self.list.height() {
+            self.remove_item(row)
+
+        } else {
+            None
+        }
+    }
+
     /// Extracts the item at the given `row` from the tree.
     ///
     /// All of the items children will be moved up one level within the tree.
     pub fn extract_item(&mut self, row: usize) -> Option<T> {
         let index = self.list.visual_index(row);
         let removed = self.list.remove(index);
-        self.focus = cmp::min(self.focus, self.list.height() - 1);
+        self.focus = cmp::min(self.focus, self.list.height().saturating_sub(1));
         removed
     }
 
@@ -290,6 +492,7 @@ impl<T: Display> TreeView<T> {
     pub fn collapse_item(&mut self, row: usize) {
         let index = self.list.visual_index(row);
         self.list.set_collapsed(index, true);
+        self.focus = cmp::min(self.focus, self.list.height().saturating_sub(1));
     }
 
     /// Expands the children of the given `row`.
@@ -302,6 +505,7 @@ impl<T: Display> TreeView<T> {
     pub fn set_collapsed(&mut self, row: usize, collapsed: bool) {
         let index = self.list.visual_index(row);
         self.list.set_collapsed(index, collapsed);
+        self.focus = cmp::min(self.focus, self.list.height().saturating_sub(1));
     }
 
     /// Collapses or expands the children of the given `row`.
@@ -311,40 +515,227 @@ impl<T: Display> TreeView<T> {
         self.with(|t| t.set_collapsed(row, collapsed))
     }
 
+    /// Collapses or expands the given `row`, along with every one of its
+    /// descendants.
+    pub fn set_collapsed_recursive(&mut self, row: usize, collapsed: bool) {
+        let index = self.list.visual_index(row);
+        self.list.set_collapsed_recursive(index, collapsed);
+        self.focus = cmp::min(self.focus, self.list.height().saturating_sub(1));
+    }
+
+    /// Collapses every item in the tree.
+    pub fn collapse_all(&mut self) {
+        self.list.set_collapsed_all(true);
+        self.focus = cmp::min(self.focus, self.list.height().saturating_sub(1));
+    }
+
+    /// Expands every item in the tree.
+    pub fn expand_all(&mut self) {
+        self.list.set_collapsed_all(false);
+    }
+
+    /// Narrows the displayed rows to items whose `Display` text contains
+    /// `query`, keeping all ancestors of a match visible so that matches
+    /// retain their context. Passing an empty `query` restores the full
+    /// tree.
+    ///
+    /// While a filter is in effect, `n`/`N` can be used to move the focus
+    /// to the next/previous matching row.
+    pub fn set_filter(&mut self, query: &str) {
+        if query.is_empty() {
+            self.list.clear_filter();
+
+        } else {
+            self.list.apply_filter(query);
+        }
+
+        self.focus = 0;
+        self.scrollbase.scroll_to(0);
+    }
+
 }
 
 impl<T: Display> TreeView<T> {
 
+    // Inserts the children loaded for the lazy item at `row` and marks it
+    // as loaded and expanded.
+    fn load_children(&mut self, row: usize, children: Vec<(T, Placement)>) {
+        let index = self.list.visual_index(row);
+
+        let mut anchor_index = index;
+        for (value, placement) in children {
+            anchor_index = self.list.insert(placement, anchor_index, value);
+        }
+
+        self.list.set_loaded(index, true);
+        self.list.set_collapsed(index, false);
+    }
+
+    // Fetches lazy children if not yet loaded, otherwise toggles the
+    // collapsed state of an expandable row, or fires `on_submit` for a
+    // leaf. Returns `None` when the row has no actionable callback to
+    // invoke, in which case the caller should fall through to the regular
+    // focus/select handling.
+    fn activate_row(&mut self, row: usize) -> Option<EventResult> {
+        let index = self.list.visual_index(row);
+
+        if self.list.get_lazy(index) && !self.list.get_loaded(index) {
+            if let Some(cb) = self.on_expand.clone() {
+                // Loading a lazy item requires looking the view back up via
+                // `Cursive::call_on_id` once the callback has `&mut Cursive`,
+                // which only works if the view was wrapped with
+                // `TreeView::with_id`. Without that, there is no way to
+                // deliver the loaded children, so ignore the keystroke
+                // instead of panicking on what looks like an ordinary expand.
+                if self.id.is_empty() {
+                    return Some(EventResult::Ignored);
+                }
+
+                let id = self.id.clone();
+                return Some(EventResult::Consumed(Some(Callback::from_fn(move |s| {
+                    let children = cb(s, row);
+                    let on_collapse = s.call_on_id(id.as_str(), |tree: &mut TreeView<T>| {
+                        tree.load_children(row, children);
+                        tree.on_collapse.clone()
+                    });
+
+                    if let Some(Some(on_collapse)) = on_collapse {
+                        on_collapse(s, row, false);
+                    }
+                }))));
+            }
+        }
+
+        let collapsed = self.list.get_collapsed(index);
+        let children = self.list.get_children(index);
+
+        if children > 0 {
+
+            self.list.set_collapsed(index, !collapsed);
+
+            if self.on_collapse.is_some() {
+                let cb = self.on_collapse.clone().unwrap();
+                return Some(EventResult::Consumed(Some(Callback::from_fn(move |s| {
+                    cb(s, row, !collapsed)
+                }))));
+            }
+
+        } else if self.on_submit.is_some() {
+            let cb = self.on_submit.clone().unwrap();
+            return Some(EventResult::Consumed(Some(Callback::from_fn(move |s| {
+                cb(s, row)
+            }))));
+        }
+
+        None
+    }
+
+    // Returns `true` if there are currently no visible rows to focus. This
+    // can happen even when the tree itself holds items, if an active
+    // filter matches none of them.
+    fn no_visible_rows(&self) -> bool {
+        self.list.height() == 0
+    }
+
     fn focus_up(&mut self, n: usize) {
         self.focus -= cmp::min(self.focus, n);
     }
 
     fn focus_down(&mut self, n: usize) {
-        self.focus = cmp::min(self.focus + n, self.list.height() - 1);
+        self.focus = cmp::min(self.focus + n, self.list.height().saturating_sub(1));
+    }
+
+    // Moves the focus to the next (or, if `reverse`, the previous) row
+    // that itself matches the active filter, wrapping around.
+    fn focus_to_match(&mut self, reverse: bool) {
+        let indices = self.list.visible_indices();
+        if indices.is_empty() {
+            return;
+        }
+
+        let count = indices.len();
+        let mut row = self.focus;
+        for _ in 0..count {
+            row = if reverse {
+                if row == 0 { count - 1 } else { row - 1 }
+            } else {
+                (row + 1) % count
+            };
+
+            if self.list.get_matched(indices[row]) {
+                self.focus = row;
+                break;
+            }
+        }
+
+        self.scrollbase.scroll_to(self.focus);
+    }
+
+    // Re-applies the current contents of `search_query` as the active
+    // filter, narrowing the tree as the user types.
+    fn apply_search_query(&mut self) {
+        let query = self.search_query.clone();
+        self.set_filter(&query);
+    }
+
+    // Handles a single keystroke while interactive search (entered via
+    // `/`) is active.
+    fn on_search_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Key(Key::Esc) => {
+                self.searching = false;
+                self.search_query.clear();
+                self.list.clear_filter();
+            },
+            Event::Key(Key::Enter) => {
+                self.searching = false;
+            },
+            Event::Key(Key::Backspace) => {
+                self.search_query.pop();
+                self.apply_search_query();
+            },
+            Event::Char(c) => {
+                self.search_query.push(c);
+                self.apply_search_query();
+            },
+            _ => return EventResult::Ignored
+        }
+
+        EventResult::Consumed(None)
     }
 
 }
 
-impl<T: Display> View for TreeView<T> {
+impl<T: Display + 'static> View for TreeView<T> {
 
     fn draw(&self, printer: &Printer) {
 
+        let indices = self.list.visible_indices();
         let items = self.list.items();
-        let list_index = Rc::new(RefCell::new(self.scrollbase.start_line));
 
         self.scrollbase.draw(printer, |printer, i| {
 
-            let mut index = list_index.borrow_mut();
-            let item = &items[*index];
-
-            if item.collapsed {
-                *index += item.children + 1;
+            let item = &items[indices[i]];
+            let focused = i == self.focus;
+            let has_children = item.children > 0 || (item.lazy && !item.loaded);
+
+            if let Some(renderer) = self.renderer.as_ref() {
+                let context = RowContext {
+                    level: item.level,
+                    collapsed: item.collapsed,
+                    has_children,
+                    focused
+                };
+
+                printer.print_styled(
+                    (item.level * 2, 0),
+                    &renderer(&item.value, context)
+                );
 
-            } else {
-                *index += 1;
-            };
+                return;
+            }
 
-            let color = if i == self.focus {
+            let color = if focused {
                 if self.enabled && printer.focused {
                     ColorStyle::Highlight
 
@@ -356,7 +747,7 @@ impl<T: Display> View for TreeView<T> {
                 ColorStyle::Primary
             };
 
-            if item.children > 0 {
+            if has_children {
                 if item.collapsed {
                     printer.print((item.level * 2, 0), "▸");
 
@@ -381,12 +772,29 @@ impl<T: Display> View for TreeView<T> {
 
     fn required_size(&mut self, req: Vec2) -> Vec2 {
 
-        let width: usize = self.list.items().iter().map(|item| {
-            item.level * 2 + format!("{}", item.value).len() + 2
+        let indices = self.list.visible_indices();
+        let items = self.list.items();
+
+        let width: usize = indices.iter().map(|&index| {
+            let item = &items[index];
+
+            if let Some(renderer) = self.renderer.as_ref() {
+                let context = RowContext {
+                    level: item.level,
+                    collapsed: item.collapsed,
+                    has_children: item.children > 0 || (item.lazy && !item.loaded),
+                    focused: false
+                };
+
+                item.level * 2 + renderer(&item.value, context).source().len()
+
+            } else {
+                item.level * 2 + format!("{}", item.value).len() + 2
+            }
 
         }).max().unwrap_or(0);
 
-        let h = self.list.height();
+        let h = indices.len();
         let w = if req.y < h {
             width + 2
 
@@ -406,7 +814,7 @@ impl<T: Display> View for TreeView<T> {
     }
 
     fn take_focus(&mut self, _: Direction) -> bool {
-        self.enabled && !self.is_empty()
+        self.enabled && !self.no_visible_rows()
     }
 
     fn on_event(&mut self, event: Event) -> EventResult {
@@ -415,8 +823,25 @@ impl<T: Display> View for TreeView<T> {
             return EventResult::Ignored;
         }
 
+        if self.searching {
+            return self.on_search_event(event);
+        }
+
         let last_focus = self.focus;
         match event {
+            Event::Char('/') => {
+                self.searching = true;
+                self.search_query.clear();
+                return EventResult::Consumed(None);
+            },
+            Event::Char('n') if self.list.is_filtering() => {
+                self.focus_to_match(false);
+                return EventResult::Consumed(None);
+            },
+            Event::Char('N') if self.list.is_filtering() => {
+                self.focus_to_match(true);
+                return EventResult::Consumed(None);
+            },
             Event::Key(Key::Up) if self.focus > 0 => {
                 self.focus_up(1);
             },
@@ -433,40 +858,114 @@ impl<T: Display> View for TreeView<T> {
                 self.focus = 0;
             },
             Event::Key(Key::End) => {
-                self.focus = self.list.height() - 1;
+                self.focus = self.list.height().saturating_sub(1);
             },
-            Event::Key(Key::Enter) => if !self.is_empty() {
-
+            Event::Key(Key::Enter) => if !self.no_visible_rows() {
+                let row = self.focus;
+                if let Some(result) = self.activate_row(row) {
+                    return result;
+                }
+            },
+            Event::Key(Key::Right) => if !self.no_visible_rows() {
                 let row = self.focus;
                 let index = self.list.visual_index(row);
-                let collapsed = self.list.get_collapsed(index);
-                let children = self.list.get_children(index);
 
-                if children > 0 {
+                if self.list.get_lazy(index) && !self.list.get_loaded(index) {
+                    if let Some(result) = self.activate_row(row) {
+                        return result;
+                    }
+                }
 
-                    self.list.set_collapsed(index, !collapsed);
+                if self.list.get_children(index) > 0 && self.list.get_collapsed(index) {
+                    self.list.set_collapsed(index, false);
 
-                    if self.on_collapse.is_some() {
-                        let cb = self.on_collapse.clone().unwrap();
+                    if let Some(cb) = self.on_collapse.clone() {
                         return EventResult::Consumed(Some(Callback::from_fn(move |s| {
-                            cb(s, row, !collapsed)
+                            cb(s, row, false)
                         })));
                     }
 
-                } else if self.on_submit.is_some() {
-                    let cb = self.on_submit.clone().unwrap();
-                    return EventResult::Consumed(Some(Callback::from_fn(move |s| {
-                        cb(s, row)
-                    })));
+                } else if let Some(child_index) = self.list.first_child_index(index) {
+                    if let Some(child_row) = self.list.visible_indices().iter().position(|&i| i == child_index) {
+                        self.focus = child_row;
+                    }
                 }
             },
+            Event::Key(Key::Left) => if !self.no_visible_rows() {
+                let row = self.focus;
+                let index = self.list.visual_index(row);
+
+                if self.list.get_children(index) > 0 && !self.list.get_collapsed(index) {
+                    self.list.set_collapsed(index, true);
+
+                    if let Some(cb) = self.on_collapse.clone() {
+                        return EventResult::Consumed(Some(Callback::from_fn(move |s| {
+                            cb(s, row, true)
+                        })));
+                    }
+
+                } else if let Some(parent_index) = self.list.parent_index(index) {
+                    if let Some(parent_row) = self.list.visible_indices().iter().position(|&i| i == parent_index) {
+                        self.focus = parent_row;
+                    }
+                }
+            },
+            Event::Mouse { event: MouseEvent::WheelUp, .. } if self.scrollbase.can_scroll_up() => {
+                self.scrollbase.scroll_up(1);
+                return EventResult::Consumed(None);
+            },
+            Event::Mouse { event: MouseEvent::WheelDown, .. } if self.scrollbase.can_scroll_down() => {
+                self.scrollbase.scroll_down(1);
+                return EventResult::Consumed(None);
+            },
+            Event::Mouse { offset, position, event: MouseEvent::Press(MouseButton::Left) } => {
+                if let Some(position) = position.checked_sub(offset) {
+                    if !self.no_visible_rows() && position.y < self.scrollbase.view_height {
+                        let row = cmp::min(position.y + self.scrollbase.start_line, self.list.height() - 1);
+                        let index = self.list.visual_index(row);
+                        let level = self.list.items()[index].level;
+
+                        if position.x == level * 2 {
+                            if let Some(result) = self.activate_row(row) {
+                                return result;
+                            }
+
+                        } else {
+                            self.focus = row;
+                        }
+                    }
+                }
+            },
+            Event::Mouse { offset, position, event: MouseEvent::Release(MouseButton::Left) } => {
+                if let Some(position) = position.checked_sub(offset) {
+                    if !self.no_visible_rows() && position.y < self.scrollbase.view_height {
+                        let row = cmp::min(position.y + self.scrollbase.start_line, self.list.height() - 1);
+
+                        if row == self.focus {
+                            let index = self.list.visual_index(row);
+                            let leaf = self.list.get_children(index) == 0
+                                && !(self.list.get_lazy(index) && !self.list.get_loaded(index));
+
+                            if leaf {
+                                if let Some(cb) = self.on_submit.clone() {
+                                    return EventResult::Consumed(Some(Callback::from_fn(move |s| {
+                                        cb(s, row)
+                                    })));
+                                }
+                            }
+                        }
+                    }
+                }
+
+                return EventResult::Ignored;
+            },
             _ => return EventResult::Ignored
         }
 
         let focus = self.focus;
         self.scrollbase.scroll_to(focus);
 
-        if !self.is_empty() && last_focus != focus {
+        if !self.no_visible_rows() && last_focus != focus {
             let row = self.focus;
             EventResult::Consumed(self.on_select.clone().map(|cb| {
                 Callback::from_fn(move |s| cb(s, row))
@@ -480,3 +979,129 @@ impl<T: Display> View for TreeView<T> {
 
 }
 
+#[cfg(test)]
+mod tests {
+
+    use super::{Event, EventResult, MouseButton, MouseEvent, Placement, RowContext, StyledString, TreeView};
+
+    fn tree() -> TreeView<String> {
+        let mut tree = TreeView::new();
+        tree.insert_item("root".to_string(), Placement::Child, 0);
+        tree.insert_item("child-a".to_string(), Placement::Child, 0);
+        tree.insert_item("child-b".to_string(), Placement::Sibling, 1);
+        tree
+    }
+
+    #[test]
+    fn try_remove_item_out_of_range_leaves_the_tree_untouched() {
+        let mut tree = tree();
+
+        assert_eq!(tree.try_remove_item(tree.len()), None);
+        assert_eq!(tree.len(), 3);
+    }
+
+    #[test]
+    fn remove_item_down_to_empty_does_not_panic() {
+        let mut tree = tree();
+
+        while !tree.is_empty() {
+            let last_row = tree.len() - 1;
+            assert!(tree.try_remove_item(last_row).is_some());
+        }
+
+        assert_eq!(tree.len(), 0);
+        assert_eq!(tree.row(), None);
+    }
+
+    #[test]
+    fn set_collapsed_recursive_hides_every_descendant() {
+        let mut tree = tree();
+
+        tree.set_collapsed_recursive(0, true);
+
+        assert_eq!(tree.list.height(), 1);
+        assert_eq!(tree.row(), Some(0));
+    }
+
+    #[test]
+    fn collapse_all_clamps_focus_onto_a_visible_row() {
+        let mut tree = tree();
+        tree.set_selected_row(2);
+
+        tree.collapse_all();
+
+        assert_eq!(tree.list.height(), 1);
+        assert_eq!(tree.row(), Some(0));
+    }
+
+    #[test]
+    fn required_size_measures_the_default_rendering() {
+        let mut tree: TreeView<String> = TreeView::new();
+        tree.insert_item("root".to_string(), Placement::Child, 0);
+        tree.insert_item("ab".to_string(), Placement::Child, 0);
+
+        let size = tree.required_size((0, 0).into());
+
+        // Both rows measure to the same width: "root" is level 0, 4 chars
+        // wide, plus the glyph/padding (2) => 6; "ab" is nested one level
+        // deeper (2) but only 2 chars wide, plus padding (2) => 6 as well.
+        // The final +2 accounts for the scrollbar, since the requested
+        // height (0) is less than the two rows being shown.
+        assert_eq!(size.x, 8);
+        assert_eq!(size.y, 2);
+    }
+
+    #[test]
+    fn required_size_measures_a_custom_renderer() {
+        let mut tree: TreeView<String> = TreeView::new();
+        tree.insert_item("root".to_string(), Placement::Child, 0);
+        tree.set_renderer(|value: &String, context: RowContext| {
+            StyledString::plain(format!("{}{}", "x".repeat(context.level), value))
+        });
+
+        let size = tree.required_size((0, 0).into());
+
+        // level 0 => no "x" prefix, the rendered "root" is 4 chars wide,
+        // plus the +2 reserved for the scrollbar since the requested
+        // height (0) is less than the single row being shown.
+        assert_eq!(size.x, 6);
+        assert_eq!(size.y, 1);
+    }
+
+    #[test]
+    fn mouse_click_on_a_row_moves_focus_there() {
+        let mut tree = tree();
+        tree.layout((10, 3).into());
+
+        let result = tree.on_event(Event::Mouse {
+            offset: (0, 0).into(),
+            position: (5, 1).into(),
+            event: MouseEvent::Press(MouseButton::Left)
+        });
+
+        let consumed = match result {
+            EventResult::Consumed(_) => true,
+            EventResult::Ignored => false
+        };
+        assert!(consumed);
+        assert_eq!(tree.row(), Some(1));
+    }
+
+    #[test]
+    fn mouse_click_on_the_fold_glyph_toggles_collapse() {
+        let mut tree = tree();
+        tree.layout((10, 3).into());
+
+        assert!(!tree.list.get_collapsed(0));
+
+        tree.on_event(Event::Mouse {
+            offset: (0, 0).into(),
+            position: (0, 0).into(),
+            event: MouseEvent::Press(MouseButton::Left)
+        });
+
+        assert!(tree.list.get_collapsed(0));
+    }
+
+}
+
@@ -0,0 +1,684 @@
+//! Flattened, pre-order storage for the items of a `TreeView`.
+
+// STD Dependencies -----------------------------------------------------------
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt::Display;
+
+/// Describes where a new item should be placed relative to an existing item
+/// when calling `TreeView::insert_item`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Insert the new item as a child of the referenced item.
+    Child,
+    /// Insert the new item as a sibling, located directly after the
+    /// referenced item (and all of its children).
+    Sibling
+}
+
+/// A single entry of the flattened tree.
+pub struct Item<T> {
+    /// The wrapped value of the item.
+    pub value: T,
+    /// The nesting depth of the item, `0` for top level items.
+    pub level: usize,
+    /// The total number of descendants (children, grandchildren, ...) of
+    /// this item.
+    pub children: usize,
+    /// Whether the children of this item are currently hidden.
+    pub collapsed: bool,
+    /// Whether this item's children are loaded on demand rather than being
+    /// fully materialized up front.
+    pub lazy: bool,
+    /// Whether a lazy item's children have already been loaded.
+    pub loaded: bool,
+    /// Whether this item matches the active filter, or is an ancestor of a
+    /// matching item. Always `true` while no filter is active.
+    pub filtered_visible: bool,
+    /// Whether this item itself (rather than merely one of its
+    /// descendants) matches the active filter.
+    pub matched: bool
+}
+
+impl<T> Item<T> {
+    fn new(value: T, level: usize, lazy: bool) -> Self {
+        Self {
+            value,
+            level,
+            children: 0,
+            collapsed: lazy,
+            lazy,
+            loaded: !lazy,
+            filtered_visible: true,
+            matched: false
+        }
+    }
+}
+
+/// A flattened, pre-order list of tree items.
+pub struct TreeList<T> {
+    items: Vec<Item<T>>,
+    filter: Option<String>,
+    // Lazily (re)computed by `rebuild_visible_cache`, and invalidated by
+    // every mutation that can change which rows are visible. `height`,
+    // `visual_index` and `visible_indices` are called repeatedly per draw,
+    // per layout, and several times per navigation keystroke, so recomputing
+    // this from scratch on every call would make large/lazily-loaded trees
+    // scale poorly.
+    visible_cache: RefCell<Option<Vec<usize>>>
+}
+
+impl<T> TreeList<T> {
+
+    /// Creates a new, empty `TreeList`.
+    pub fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            filter: None,
+            visible_cache: RefCell::new(None)
+        }
+    }
+
+    /// Removes all items from the list.
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.invalidate_visible_cache();
+    }
+
+    /// Removes all items from the list, returning them in top to bottom
+    /// order.
+    pub fn take_items(&mut self) -> Vec<T> {
+        self.invalidate_visible_cache();
+        self.items.drain(..).map(|item| item.value).collect()
+    }
+
+    /// Returns the total number of items in the list, visible or not.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns `true` if the list has no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the full, flattened list of items.
+    pub fn items(&self) -> &Vec<Item<T>> {
+        &self.items
+    }
+
+    /// Returns a reference to the item's value at the given list index.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.items.get(index).map(|item| &item.value)
+    }
+
+    /// Returns a mutable reference to the item's value at the given list
+    /// index.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.items.get_mut(index).map(|item| &mut item.value)
+    }
+
+    /// Returns whether the item at the given list index is collapsed.
+    pub fn get_collapsed(&self, index: usize) -> bool {
+        self.items[index].collapsed
+    }
+
+    /// Sets whether the item at the given list index is collapsed.
+    pub fn set_collapsed(&mut self, index: usize, collapsed: bool) {
+        self.items[index].collapsed = collapsed;
+        self.invalidate_visible_cache();
+    }
+
+    /// Returns the total number of descendants of the item at the given
+    /// list index.
+    pub fn get_children(&self, index: usize) -> usize {
+        self.items[index].children
+    }
+
+    /// Sets the collapsed state of the item at the given list index, along
+    /// with every one of its descendants.
+    pub fn set_collapsed_recursive(&mut self, index: usize, collapsed: bool) {
+        let span = self.items[index].children + 1;
+        for item in &mut self.items[index..index + span] {
+            item.collapsed = collapsed;
+        }
+        self.invalidate_visible_cache();
+    }
+
+    /// Sets the collapsed state of every item in the list.
+    pub fn set_collapsed_all(&mut self, collapsed: bool) {
+        for item in &mut self.items {
+            item.collapsed = collapsed;
+        }
+        self.invalidate_visible_cache();
+    }
+
+    /// Returns whether the item at the given list index loads its children
+    /// on demand.
+    pub fn get_lazy(&self, index: usize) -> bool {
+        self.items[index].lazy
+    }
+
+    /// Returns whether a lazy item at the given list index has already had
+    /// its children loaded.
+    pub fn get_loaded(&self, index: usize) -> bool {
+        self.items[index].loaded
+    }
+
+    /// Marks the item at the given list index as having its children
+    /// loaded.
+    pub fn set_loaded(&mut self, index: usize, loaded: bool) {
+        self.items[index].loaded = loaded;
+    }
+
+    /// Returns `true` if a filter is currently narrowing the visible items.
+    pub fn is_filtering(&self) -> bool {
+        self.filter.is_some()
+    }
+
+    /// Returns whether the item at the given list index matches the
+    /// active filter, or is an ancestor of a matching item.
+    pub fn get_filtered_visible(&self, index: usize) -> bool {
+        self.items[index].filtered_visible
+    }
+
+    /// Returns whether the item at the given list index itself matches the
+    /// active filter, as opposed to merely being an ancestor of a match.
+    pub fn get_matched(&self, index: usize) -> bool {
+        self.items[index].matched
+    }
+
+    /// Returns the number of currently visible rows, taking collapsed
+    /// subtrees and the active filter into account.
+    pub fn height(&self) -> usize {
+        self.rebuild_visible_cache();
+        self.visible_cache.borrow().as_ref().unwrap().len()
+    }
+
+    /// Converts a visible row index into an index into the flattened item
+    /// list.
+    pub fn visual_index(&self, row: usize) -> usize {
+        self.rebuild_visible_cache();
+        self.visible_cache.borrow().as_ref().unwrap().get(row).cloned().unwrap_or_else(|| self.items.len())
+    }
+
+    /// Returns, in display order, the indices into the flattened item list
+    /// of all currently visible rows.
+    ///
+    /// While no filter is active this skips the contents of collapsed
+    /// subtrees. While a filter is active it instead skips every item that
+    /// is neither a match nor an ancestor of a match, ignoring the
+    /// collapsed state so that matches always remain reachable.
+    ///
+    /// The result is cached internally and only recomputed after a mutation
+    /// that could change which rows are visible, so repeated calls between
+    /// mutations are cheap.
+    pub fn visible_indices(&self) -> Vec<usize> {
+        self.rebuild_visible_cache();
+        self.visible_cache.borrow().as_ref().unwrap().clone()
+    }
+
+    // (Re)computes `visible_cache` if it was invalidated since the last
+    // call, leaving it populated either way.
+    fn rebuild_visible_cache(&self) {
+        if self.visible_cache.borrow().is_some() {
+            return;
+        }
+
+        let mut indices = Vec::new();
+        let mut index = 0;
+
+        if self.filter.is_some() {
+            while index < self.items.len() {
+                if self.items[index].filtered_visible {
+                    indices.push(index);
+                }
+                index += 1;
+            }
+
+        } else {
+            while index < self.items.len() {
+                indices.push(index);
+                index += self.skip(index);
+            }
+        }
+
+        *self.visible_cache.borrow_mut() = Some(indices);
+    }
+
+    // Drops the cached visible-row list so it gets recomputed the next time
+    // it is needed. Called by every mutation that can change which items
+    // are visible (inserting/removing items, changing collapsed state,
+    // reordering children, or applying/clearing a filter).
+    fn invalidate_visible_cache(&mut self) {
+        *self.visible_cache.get_mut() = None;
+    }
+
+    /// Returns the list index of the parent of the item at `index`, or
+    /// `None` if it is a top-level item.
+    pub fn parent_index(&self, index: usize) -> Option<usize> {
+        let level = self.items[index].level;
+        if level == 0 {
+            return None;
+        }
+
+        let target_level = level - 1;
+        let mut i = index;
+        while i > 0 {
+            i -= 1;
+            if self.items[i].level == target_level {
+                return Some(i);
+            }
+        }
+
+        None
+    }
+
+    /// Returns the list index of the first child of the item at `index`, or
+    /// `None` if it has no children.
+    pub fn first_child_index(&self, index: usize) -> Option<usize> {
+        if self.items[index].children > 0 {
+            Some(index + 1)
+
+        } else {
+            None
+        }
+    }
+
+    /// Inserts a fully materialized `value` relative to the item at `index`,
+    /// returning the list index the new item occupies.
+    pub fn insert(&mut self, placement: Placement, index: usize, value: T) -> usize {
+        self.insert_item(placement, index, value, false)
+    }
+
+    /// Inserts a lazily loaded placeholder `value` relative to the item at
+    /// `index`, returning the list index the new item occupies.
+    ///
+    /// The item is created collapsed and without children until its
+    /// children are loaded via `TreeView::set_on_expand`.
+    pub fn insert_lazy(&mut self, placement: Placement, index: usize, value: T) -> usize {
+        self.insert_item(placement, index, value, true)
+    }
+
+    /// Removes the item at the given list index along with all of its
+    /// descendants, returning the removed values in top to bottom order.
+    pub fn remove_with_children(&mut self, index: usize) -> Option<Vec<T>> {
+        if index >= self.items.len() {
+            return None;
+        }
+
+        let level = self.items[index].level;
+        let span = self.items[index].children + 1;
+        self.update_ancestors(index, level, -(span as isize));
+        self.invalidate_visible_cache();
+
+        Some(self.items.drain(index..index + span).map(|item| item.value).collect())
+    }
+
+    /// Removes the item at the given list index, moving its children up by
+    /// one level within the tree.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.items.len() {
+            return None;
+        }
+
+        let level = self.items[index].level;
+        let children = self.items[index].children;
+        self.update_ancestors(index, level, -1);
+
+        for item in &mut self.items[index + 1..index + 1 + children] {
+            item.level -= 1;
+        }
+        self.invalidate_visible_cache();
+
+        Some(self.items.remove(index).value)
+    }
+
+    fn insert_item(&mut self, placement: Placement, index: usize, value: T, lazy: bool) -> usize {
+        let (insert_index, level) = if self.items.is_empty() {
+            (0, 0)
+
+        } else {
+            match placement {
+                Placement::Child => (index + 1, self.items[index].level + 1),
+                Placement::Sibling => (
+                    index + self.items[index].children + 1,
+                    self.items[index].level
+                )
+            }
+        };
+
+        self.items.insert(insert_index, Item::new(value, level, lazy));
+        self.update_ancestors(insert_index, level, 1);
+        self.invalidate_visible_cache();
+
+        insert_index
+    }
+
+    // Returns the number of flattened rows to skip past the item at `index`
+    // in order to reach the next visible row, taking its collapsed state
+    // into account.
+    fn skip(&self, index: usize) -> usize {
+        let item = &self.items[index];
+        if item.collapsed {
+            item.children + 1
+
+        } else {
+            1
+        }
+    }
+
+    // Walks the ancestor chain of the item located at `index` (whose level
+    // is `level`), adjusting each ancestor's `children` count by `delta`.
+    fn update_ancestors(&mut self, index: usize, level: usize, delta: isize) {
+        if level == 0 {
+            return;
+        }
+
+        let mut target_level = level - 1;
+        let mut i = index;
+        loop {
+            if i == 0 {
+                break;
+            }
+
+            i -= 1;
+            if self.items[i].level == target_level {
+                self.items[i].children = (self.items[i].children as isize + delta) as usize;
+                if target_level == 0 {
+                    break;
+                }
+                target_level -= 1;
+            }
+        }
+    }
+
+    /// Reorders the direct children of the item at `index` according to
+    /// `cmp`, optionally recursing into each child's own children first
+    /// when `recursive` is set.
+    pub fn sort_children<F: Fn(&T, &T) -> Ordering + ?Sized>(&mut self, index: usize, recursive: bool, cmp: &F) {
+        self.sort_children_tracking(index, recursive, cmp, None);
+    }
+
+    /// Like `sort_children`, but also returns the list index the item
+    /// originally at `track` occupies once sorting has finished, so a
+    /// caller can report where an item it just inserted ended up.
+    ///
+    /// With `recursive` set, the children's own subtrees are sorted before
+    /// `track` is located, so the result is exact only for a `track` that
+    /// points at a direct child (or at the parent's only child).
+    pub fn sort_children_tracking<F: Fn(&T, &T) -> Ordering + ?Sized>(&mut self, index: usize, recursive: bool, cmp: &F, track: Option<usize>) -> Option<usize> {
+        if recursive {
+            for child_index in self.direct_child_indices(index) {
+                self.sort_children(child_index, true, cmp);
+            }
+        }
+
+        let child_indices = self.direct_child_indices(index);
+        if child_indices.len() < 2 {
+            return track;
+        }
+
+        let first = child_indices[0];
+        let total: usize = child_indices.iter().map(|&i| self.items[i].children + 1).sum();
+
+        let mut remaining: Vec<Item<T>> = self.items.drain(first..first + total).collect();
+        let mut raw_blocks = Vec::new();
+        while !remaining.is_empty() {
+            let span = remaining[0].children + 1;
+            raw_blocks.push(remaining.drain(..span).collect::<Vec<_>>());
+        }
+
+        // Locate which (still pre-sort-ordered) block the tracked index
+        // falls into, and its offset within that block, before the blocks
+        // get reordered below.
+        let tracked = track.and_then(|track| {
+            let mut offset = 0;
+            for (block_index, block) in raw_blocks.iter().enumerate() {
+                if track >= first + offset && track < first + offset + block.len() {
+                    return Some((block_index, track - (first + offset)));
+                }
+                offset += block.len();
+            }
+            None
+        });
+
+        let mut blocks: Vec<(usize, Vec<Item<T>>)> = raw_blocks.into_iter().enumerate().collect();
+        blocks.sort_by(|a, b| cmp(&a.1[0].value, &b.1[0].value));
+
+        let mut new_track = None;
+        let mut offset = 0;
+        for (block_index, block) in &blocks {
+            if tracked.map(|(tracked_index, _)| tracked_index) == Some(*block_index) {
+                new_track = tracked.map(|(_, within)| first + offset + within);
+            }
+            offset += block.len();
+        }
+
+        for (offset, item) in blocks.into_iter().flat_map(|(_, block)| block).enumerate() {
+            self.items.insert(first + offset, item);
+        }
+        self.invalidate_visible_cache();
+
+        new_track
+    }
+
+    // Returns the list indices of the direct children of the item at
+    // `index`, in their current order.
+    fn direct_child_indices(&self, index: usize) -> Vec<usize> {
+        let mut indices = Vec::new();
+        let total = self.items[index].children;
+        let mut consumed = 0;
+        let mut child_index = index + 1;
+        while consumed < total {
+            indices.push(child_index);
+            let span = self.items[child_index].children + 1;
+            consumed += span;
+            child_index += span;
+        }
+        indices
+    }
+
+}
+
+impl<T: Display> TreeList<T> {
+
+    /// Narrows the visible items to those whose `Display` text contains
+    /// `query` (case-insensitively), along with the full chain of
+    /// ancestors of every match, so that matches retain their context.
+    pub fn apply_filter(&mut self, query: &str) {
+        let query = query.to_lowercase();
+
+        for item in &mut self.items {
+            item.filtered_visible = false;
+            item.matched = false;
+        }
+
+        let matches: Vec<usize> = self.items.iter().enumerate().filter(|&(_, item)| {
+            format!("{}", item.value).to_lowercase().contains(&query)
+        }).map(|(index, _)| index).collect();
+
+        for index in matches {
+            self.items[index].filtered_visible = true;
+            self.items[index].matched = true;
+
+            let mut target_level = self.items[index].level;
+            let mut i = index;
+            while target_level > 0 && i > 0 {
+                i -= 1;
+                if self.items[i].level < target_level {
+                    self.items[i].filtered_visible = true;
+                    target_level = self.items[i].level;
+                }
+            }
+        }
+
+        self.filter = Some(query);
+        self.invalidate_visible_cache();
+    }
+
+    /// Clears the active filter, restoring the full tree.
+    pub fn clear_filter(&mut self) {
+        self.filter = None;
+        for item in &mut self.items {
+            item.filtered_visible = true;
+            item.matched = false;
+        }
+        self.invalidate_visible_cache();
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Placement, TreeList};
+
+    fn tree() -> TreeList<&'static str> {
+        // root
+        //   child-a
+        //     grandchild
+        //   child-b
+        let mut list = TreeList::new();
+        let root = list.insert(Placement::Sibling, 0, "root");
+        let child_a = list.insert(Placement::Child, root, "child-a");
+        list.insert(Placement::Child, child_a, "grandchild");
+        list.insert(Placement::Sibling, child_a, "child-b");
+        list
+    }
+
+    #[test]
+    fn insert_lazy_item_starts_collapsed_and_unloaded() {
+        let mut list = TreeList::new();
+        let root = list.insert_lazy(Placement::Sibling, 0, "root");
+
+        assert!(list.get_lazy(root));
+        assert!(!list.get_loaded(root));
+        assert!(list.get_collapsed(root));
+
+        list.set_loaded(root, true);
+        assert!(list.get_loaded(root));
+    }
+
+    #[test]
+    fn apply_filter_keeps_ancestors_of_matches_visible() {
+        let mut list = tree();
+        list.apply_filter("grandchild");
+
+        // The match itself, and every one of its ancestors, stay visible...
+        assert!(list.get_filtered_visible(0)); // root
+        assert!(list.get_filtered_visible(1)); // child-a
+        assert!(list.get_filtered_visible(2)); // grandchild
+        assert!(list.get_matched(2));
+
+        // ...but an unrelated sibling is hidden.
+        assert!(!list.get_filtered_visible(3)); // child-b
+        assert!(!list.get_matched(0));
+
+        assert_eq!(list.height(), 3);
+    }
+
+    #[test]
+    fn apply_filter_matching_nothing_hides_every_row() {
+        let mut list = tree();
+        list.apply_filter("does-not-exist");
+
+        assert_eq!(list.height(), 0);
+        assert_eq!(list.visual_index(0), list.len());
+    }
+
+    #[test]
+    fn clear_filter_restores_the_full_tree() {
+        let mut list = tree();
+        list.apply_filter("grandchild");
+        list.clear_filter();
+
+        assert_eq!(list.height(), list.len());
+        for index in 0..list.len() {
+            assert!(list.get_filtered_visible(index));
+            assert!(!list.get_matched(index));
+        }
+    }
+
+    #[test]
+    fn sort_children_reorders_direct_children_and_keeps_bookkeeping() {
+        let mut list = TreeList::new();
+        let root = list.insert(Placement::Sibling, 0, "root");
+        list.insert(Placement::Child, root, "c");
+        list.insert(Placement::Sibling, 1, "a");
+        list.insert(Placement::Sibling, 2, "b");
+
+        list.sort_children(root, false, &|a: &&str, b: &&str| a.cmp(b));
+
+        let values: Vec<&str> = (1..list.len()).map(|i| *list.get(i).unwrap()).collect();
+        assert_eq!(values, vec!["a", "b", "c"]);
+
+        assert_eq!(list.get_children(root), 3);
+        for i in 1..list.len() {
+            assert_eq!(list.items()[i].level, 1);
+        }
+    }
+
+    #[test]
+    fn sort_children_moves_each_childs_own_descendants_along_with_it() {
+        let mut list = TreeList::new();
+        let root = list.insert(Placement::Sibling, 0, "root");
+        let b = list.insert(Placement::Child, root, "b");
+        list.insert(Placement::Child, b, "b-child");
+        list.insert(Placement::Sibling, b, "a");
+
+        list.sort_children(root, false, &|a: &&str, b: &&str| a.cmp(b));
+
+        assert_eq!(*list.get(1).unwrap(), "a");
+        assert_eq!(*list.get(2).unwrap(), "b");
+        assert_eq!(*list.get(3).unwrap(), "b-child");
+        assert_eq!(list.get_children(2), 1);
+    }
+
+    #[test]
+    fn sort_children_tracking_returns_the_post_sort_position_of_a_tracked_item() {
+        let mut list = TreeList::new();
+        let root = list.insert(Placement::Sibling, 0, "root");
+        list.insert(Placement::Child, root, "c");
+        list.insert(Placement::Sibling, 1, "b");
+        let tracked = list.insert(Placement::Sibling, 2, "a");
+
+        let new_index = list.sort_children_tracking(root, false, &|a: &&str, b: &&str| a.cmp(b), Some(tracked));
+
+        assert_eq!(new_index, Some(1));
+        assert_eq!(*list.get(new_index.unwrap()).unwrap(), "a");
+    }
+
+    #[test]
+    fn height_reflects_mutations_made_after_the_cache_was_populated() {
+        let mut list = tree();
+
+        assert_eq!(list.height(), 4); // populates the cache
+
+        list.set_collapsed(1, true); // hides the grandchild
+
+        assert_eq!(list.height(), 3);
+        assert_eq!(list.visible_indices(), vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn parent_index_walks_up_to_the_nearest_shallower_item() {
+        let list = tree();
+
+        assert_eq!(list.parent_index(0), None); // root
+        assert_eq!(list.parent_index(1), Some(0)); // child-a
+        assert_eq!(list.parent_index(2), Some(1)); // grandchild
+        assert_eq!(list.parent_index(3), Some(0)); // child-b
+    }
+
+    #[test]
+    fn first_child_index_is_none_for_childless_items() {
+        let list = tree();
+
+        assert_eq!(list.first_child_index(0), Some(1)); // root -> child-a
+        assert_eq!(list.first_child_index(1), Some(2)); // child-a -> grandchild
+        assert_eq!(list.first_child_index(2), None); // grandchild has no children
+        assert_eq!(list.first_child_index(3), None); // child-b has no children
+    }
+
+}